@@ -0,0 +1,142 @@
+// Copyright 2015-2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Ethereum.
+
+// Parity Ethereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Ethereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Ethereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! `CliqueBlockState` tracks the list of authorized signers, in-progress votes and recently
+//! signing addresses for a single block in the chain. States are derived incrementally from
+//! their parent, so the engine only ever has to keep a bounded cache of recently touched blocks
+//! around rather than the full history.
+
+use std::collections::{VecDeque, HashMap};
+
+use ethereum_types::{H256, Address};
+use header::{Header, BlockNumber};
+
+use super::super::validator_set::SimpleList;
+
+/// `CliqueBlockState` holds the state of the signer set and votes at a given point in the chain.
+#[derive(Clone, Debug)]
+pub struct CliqueBlockState {
+	/// Block number of the block this state was calculated for.
+	pub number: BlockNumber,
+	/// Current list of authorized signers, kept sorted for deterministic in-turn calculation.
+	pub signers: SimpleList,
+	/// Addresses that have signed recently, used to rate-limit signers. Indexed in the order
+	/// they signed, oldest first, and bounded to `floor(signers.len() / 2)` entries, one fewer
+	/// than `recent_signer_limit()` (see `note_signer`).
+	pub recent_signers: VecDeque<Address>,
+	/// Votes that have been cast, but not yet tallied into a majority, keyed by the
+	/// `(candidate, voter)` pair that cast them.
+	pub votes: HashMap<(Address, Address), bool>,
+}
+
+impl CliqueBlockState {
+	/// Create a fresh state for a checkpoint block, with no history and no pending votes.
+	pub fn new(signers: SimpleList) -> Self {
+		CliqueBlockState {
+			number: 0,
+			signers,
+			recent_signers: VecDeque::new(),
+			votes: HashMap::new(),
+		}
+	}
+
+	/// The length, in blocks, of the recent-signer window per EIP-225: a signer may sign at most
+	/// one of any `floor(len(signers) / 2) + 1` consecutive blocks.
+	pub fn recent_signer_limit(&self) -> usize {
+		self.signers.len() / 2 + 1
+	}
+
+	/// Whether `address` is currently within the recent-signer window and therefore not
+	/// permitted to sign.
+	pub fn recently_signed(&self, address: &Address) -> bool {
+		self.recent_signers.iter().any(|a| a == address)
+	}
+
+	/// Whether `address` is an authorized signer in this state.
+	pub fn is_authorized(&self, address: &Address) -> bool {
+		self.signers.contains(address)
+	}
+
+	/// Index of `address` in the sorted signer list, used to compute in-turn-ness.
+	pub fn signer_index(&self, address: &Address) -> Option<usize> {
+		self.signers.as_list().iter().position(|a| a == address)
+	}
+
+	/// Push `signer` onto the recent-signer window, evicting the oldest entry once the window
+	/// grows past `recent_signer_limit() - 1`. The cap is one short of the limit itself: per
+	/// EIP-225 a signer is rejected while `seen > number - limit`, i.e. the window only needs to
+	/// remember the `limit - 1` most recent signers to answer `recently_signed` correctly.
+	fn note_signer(&mut self, signer: Address) {
+		self.recent_signers.push_back(signer);
+		while self.recent_signers.len() > self.recent_signer_limit().saturating_sub(1) {
+			self.recent_signers.pop_front();
+		}
+	}
+
+	fn add_signer(&mut self, address: Address) {
+		let mut signers = self.signers.as_list().clone();
+		if !signers.contains(&address) {
+			signers.push(address);
+			signers.sort();
+			self.signers = SimpleList::new(signers);
+		}
+	}
+
+	fn remove_signer(&mut self, address: &Address) {
+		let mut signers = self.signers.as_list().clone();
+		signers.retain(|a| a != address);
+		self.signers = SimpleList::new(signers);
+	}
+
+	/// Cast a vote from `voter` on `candidate`, proposing to authorize (`authorize == true`) or
+	/// remove (`authorize == false`) it. If this vote brings the tally for `candidate` to a
+	/// strict majority of the current signer set, the vote is enacted immediately: `candidate`
+	/// is added to or removed from the signer set, and every vote concerning it is discarded. If
+	/// `candidate` is removed, any vote it had itself cast is discarded too, since a former
+	/// signer's in-flight votes no longer count.
+	pub fn cast_vote(&mut self, candidate: Address, voter: Address, authorize: bool) {
+		self.votes.insert((candidate, voter), authorize);
+
+		let tally = self.votes.iter()
+			.filter(|&(&(c, _), &a)| c == candidate && a == authorize)
+			.count();
+
+		if tally * 2 <= self.signers.len() {
+			return;
+		}
+
+		if authorize {
+			self.add_signer(candidate);
+		} else {
+			self.remove_signer(&candidate);
+		}
+
+		self.votes.retain(|&(c, v), _| c != candidate && v != candidate);
+	}
+
+	/// Discard every pending vote, as happens on every checkpoint block.
+	pub fn clear_votes(&mut self) {
+		self.votes.clear();
+	}
+
+	/// Advance this state in place by one block, to the state as of `header`. `header` is assumed
+	/// to be a direct child of the block this state was computed for, and `signer` is the
+	/// address recovered from `header`'s seal.
+	pub fn apply(&mut self, header: &Header, signer: Address) {
+		self.number = header.number();
+		self.note_signer(signer);
+	}
+}
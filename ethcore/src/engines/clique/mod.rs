@@ -1,30 +1,36 @@
 mod signer_snapshot;
 mod params;
 mod step_service;
+mod block_state;
+#[cfg(test)]
+mod tests;
 
 use rlp::{encode, Decodable, DecoderError, Encodable, RlpStream, Rlp};
-use std::time::{Duration};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use std::sync::{Weak, Arc};
 use std::collections::{BTreeMap, HashMap};
 use std::{fmt, error};
 use std::str::FromStr;
-use hash::{keccak};
+use hash::{keccak, KECCAK_EMPTY_LIST_RLP};
+use lru_cache::LruCache;
+use rand::Rng;
 
 use self::params::CliqueParams;
 use self::step_service::StepService;
+use self::block_state::CliqueBlockState;
 
 use super::epoch::{PendingTransition,EpochVerifier,NoOp};
 
 use account_provider::AccountProvider;
 use builtin::Builtin;
 use vm::{EnvInfo, Schedule, CreateContractAddress, CallType, ActionValue};
-use error::Error;
+use error::{Error, BlockError};
 use header::{Header, BlockNumber, ExtendedHeader};
 use snapshot::SnapshotComponents;
 use spec::CommonParams;
 use transaction::{self, UnverifiedTransaction, SignedTransaction};
-use client::EngineClient;
+use client::{EngineClient, BlockId};
 use parking_lot::RwLock;
 use block::*;
 use io::IoService;
@@ -45,12 +51,27 @@ const SIGNER_VANITY_LENGTH: u32 = 32;  // Fixed number of extra-data prefix byte
 const SIGNER_SIG_LENGTH: u32 = 65; // Fixed number of extra-data suffix bytes reserved for signer seal
 const EXTRA_DATA_POST_LENGTH: u32 = 128;
 const NONCE_DROP_VOTE: &[u8; 8] = &[0x0; 8];
-const NONCE_AUTH_VOTE: &[u8; 8] = &[0xf; 8];
+const NONCE_AUTH_VOTE: &[u8; 8] = &[0xff; 8];
+
+// Number of recently-processed block states to keep cached in memory, keyed by block hash.
+const STATE_CACHE_NUM: usize = 128;
+
+// Difficulty assigned to an in-turn signer's block.
+const DIFF_INTURN: u8 = 2;
+// Difficulty assigned to an out-of-turn signer's block.
+const DIFF_NOTURN: u8 = 1;
 
 pub struct Clique {
   client: RwLock<Option<Weak<EngineClient>>>,
   signer: RwLock<EngineSigner>,
-  signers: Vec<Address>,
+  block_state_by_hash: RwLock<LruCache<H256, CliqueBlockState>>,
+  // Votes we'd like to cast ourselves the next time we seal a block, keyed by the candidate
+  // address being proposed and whether the vote authorizes (true) or removes (false) it.
+  proposals: RwLock<HashMap<Address, bool>>,
+  // The block number and wall-clock deadline we've committed to for our next out-of-turn seal
+  // attempt. `generate_seal` is re-entered by `step()`'s periodic timer rather than blocking, so
+  // this is how it remembers a wiggle delay already in progress for the block it's working on.
+  next_seal_attempt: RwLock<Option<(BlockNumber, Instant)>>,
   machine: EthereumMachine,
   step_service: IoService<Duration>,
   epoch_length: u64,
@@ -76,15 +97,230 @@ pub fn sig_hash(header: &Header) -> Result<H256, Error> {
 
 fn recover(header: &Header) -> Result<Public, Error> {
 	let data = header.extra_data();
+	if data.len() < SIGNER_SIG_LENGTH as usize {
+		return Err(BlockError::InvalidSealArity(Mismatch {
+			expected: SIGNER_SIG_LENGTH as usize,
+			found: data.len(),
+		}).into());
+	}
+
 	let mut sig: [u8; 65] = [0; 65];
 	sig.copy_from_slice(&data[(data.len() - SIGNER_SIG_LENGTH as usize)..]);
 
-	let msg = sig_hash(header).unwrap();
-	let pubkey = ec_recover(&Signature::from(sig), &msg).unwrap();
+	let msg = sig_hash(header)?;
+	let pubkey = ec_recover(&Signature::from(sig), &msg)?;
 
 	Ok(pubkey)
 }
 
+/// Recover the address of the signer that produced `header`'s seal.
+fn recover_creator(header: &Header) -> Result<Address, Error> {
+	recover(header).map(|pubkey| public_to_address(&pubkey))
+}
+
+/// Decode the list of signer addresses embedded in a checkpoint header's extra-data,
+/// i.e. everything between the vanity prefix and the signature suffix. The addresses are
+/// returned in the order they are embedded; they are not sorted here.
+fn extract_signers(header: &Header) -> Result<Vec<Address>, Error> {
+	let data = header.extra_data();
+	let vanity = SIGNER_VANITY_LENGTH as usize;
+	let sig = SIGNER_SIG_LENGTH as usize;
+
+	if data.len() < vanity + sig {
+		return Err(Box::new("checkpoint extra-data too short to contain vanity and signature").into());
+	}
+
+	let signers_raw = &data[vanity..(data.len() - sig)];
+	if signers_raw.len() % 20 != 0 {
+		return Err(Box::new("checkpoint signer list is not a multiple of address length").into());
+	}
+
+	Ok(signers_raw.chunks(20).map(Address::from_slice).collect())
+}
+
+/// If `header` proposes a vote (i.e. its beneficiary is non-zero), interpret the nonce as an
+/// authorize/drop vote and cast it against `state`. A no-op for blocks that aren't voting.
+fn apply_vote(state: &mut CliqueBlockState, header: &Header) -> Result<(), Error> {
+	let candidate = *header.author();
+	if candidate == [0; 20].into() {
+		return Ok(());
+	}
+
+	let seal = header.decode_seal::<Vec<&[u8]>>()?;
+	let nonce = seal.get(1).ok_or_else(|| Box::new("seal is missing the nonce field").into())?;
+	let authorize = if *nonce == &NONCE_AUTH_VOTE[..] {
+		true
+	} else if *nonce == &NONCE_DROP_VOTE[..] {
+		false
+	} else {
+		return Err(Box::new("invalid clique vote nonce, must be AUTH or DROP").into());
+	};
+
+	state.cast_vote(candidate, recover_creator(header)?, authorize);
+	Ok(())
+}
+
+/// Check that a checkpoint `header`'s embedded signer list is exactly `expected` (sorted), the
+/// active signer set carried over from its parent. Checkpoints cast no votes of their own, so
+/// anything else means the checkpoint was tampered with or a vote was silently dropped. Returns
+/// the decoded, sorted signer list on success so callers can build a `CliqueBlockState` from it
+/// without decoding twice.
+fn verify_checkpoint_signers(header: &Header, expected: &[Address]) -> Result<Vec<Address>, Error> {
+	let mut signers = extract_signers(header)?;
+	signers.sort();
+
+	let mut expected = expected.to_vec();
+	expected.sort();
+
+	if signers != expected {
+		return Err(Box::new(format!(
+			"clique checkpoint #{} embeds signer list {:?}, but the parent state's signer set is {:?}",
+			header.number(), signers, expected,
+		)).into());
+	}
+
+	Ok(signers)
+}
+
+/// Context-free structural checks for `header`: timestamp drift, extra-data layout (vanity,
+/// checkpoint signer list, signature), seal nonce/mix-hash, absence of uncles, and that the
+/// difficulty is one of the two Clique-meaningful values. Pulled out of `Engine::verify_block_basic`
+/// as a plain function, over `epoch_length`/`period` rather than `&Clique`, so it can be exercised
+/// directly in tests without constructing a full engine.
+fn verify_basic(header: &Header, epoch_length: u64, period: u64) -> Result<(), Error> {
+  if header.number() == 0 {
+    return Err(Box::new("cannot verify genesis block with verify_block_basic").into());
+  }
+
+  // don't allow blocks from the future, modulo the signing period as clock-drift tolerance
+  let limit = SystemTime::now().duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or(0) + period;
+  if header.timestamp() > limit {
+    return Err(BlockError::TemporarilyInvalid(OutOfBounds {
+      min: None,
+      max: Some(limit),
+      found: header.timestamp(),
+    }).into());
+  }
+
+  let is_checkpoint = header.number() % epoch_length == 0;
+  let vanity_and_sig = SIGNER_VANITY_LENGTH as usize + SIGNER_SIG_LENGTH as usize;
+  let extra_data_len = header.extra_data().len();
+
+  if is_checkpoint {
+    // Checkpoint blocks need to enforce zero beneficiary, and carry a sorted signer list
+    // between the vanity and signature.
+    if header.author() != &[0; 20].into() {
+      return Err(EngineError::NotAuthorized([0; 20].into()).into());
+    }
+
+    if extra_data_len <= vanity_and_sig || (extra_data_len - vanity_and_sig) % 20 != 0 {
+      return Err(BlockError::ExtraDataOutOfBounds(OutOfBounds {
+        min: Some(vanity_and_sig + 20),
+        max: None,
+        found: extra_data_len,
+      }).into());
+    }
+
+    let signers = extract_signers(header)?;
+    let mut sorted_signers = signers.clone();
+    sorted_signers.sort();
+    if signers != sorted_signers {
+      return Err(BlockError::InvalidSeal.into());
+    }
+  } else if extra_data_len != vanity_and_sig {
+    return Err(BlockError::ExtraDataOutOfBounds(OutOfBounds {
+      min: Some(vanity_and_sig),
+      max: Some(vanity_and_sig),
+      found: extra_data_len,
+    }).into());
+  }
+
+  // Nonces must be 0x00..0 or 0xff..f, zeroes enforced on checkpoints
+  let seal = header.decode_seal::<Vec<&[u8]>>()?;
+  let mix_hash = seal.get(0).ok_or_else(|| Error::from(BlockError::InvalidSeal))?;
+  let nonce = seal.get(1).ok_or_else(|| Error::from(BlockError::InvalidSeal))?;
+
+  let is_auth_vote = *nonce == &NONCE_AUTH_VOTE[..];
+  let is_drop_vote = *nonce == &NONCE_DROP_VOTE[..];
+  if !is_auth_vote && !is_drop_vote {
+    return Err(BlockError::InvalidSeal.into());
+  }
+  if is_checkpoint && !is_drop_vote {
+    return Err(BlockError::InvalidSeal.into());
+  }
+
+  // Ensure that the mix digest is zero, as Clique has no fork-choice use for it
+  let zero_mix_hash = H256::zero();
+  if *mix_hash != zero_mix_hash.as_bytes() {
+    return Err(BlockError::InvalidSeal.into());
+  }
+
+  // Ensure that the block doesn't contain any uncles, which are meaningless in PoA
+  if *header.uncles_hash() != KECCAK_EMPTY_LIST_RLP {
+    return Err(BlockError::InvalidUnclesHash(Mismatch {
+      expected: KECCAK_EMPTY_LIST_RLP,
+      found: *header.uncles_hash(),
+    }).into());
+  }
+
+  // Ensure that the block's difficulty is one of the two Clique-meaningful values
+  if header.difficulty() != &U256::from(DIFF_INTURN) && header.difficulty() != &U256::from(DIFF_NOTURN) {
+    return Err(BlockError::DifficultyOutOfBounds(OutOfBounds {
+      min: Some(U256::from(DIFF_NOTURN)),
+      max: Some(U256::from(DIFF_INTURN)),
+      found: *header.difficulty(),
+    }).into());
+  }
+
+  Ok(())
+}
+
+/// `header`'s seal must recover to *some* signature. This deliberately stops short of confirming
+/// the recovered address is in the active signer set: that membership check needs the parent's
+/// derived state, which this hook doesn't receive and which isn't guaranteed to be available yet
+/// during sync (a header can arrive before its parent is imported). Verifying membership here
+/// would make an "unordered" check depend on import order, rejecting otherwise-valid headers; it
+/// is performed in `verify_family` instead, once the parent is guaranteed to be present.
+fn verify_unordered(header: &Header) -> Result<(), Error> {
+  if header.number() == 0 {
+    return Ok(());
+  }
+
+  recover_creator(header)?;
+  Ok(())
+}
+
+/// Checks on `header` that depend on `state`, the derived `CliqueBlockState` of its parent:
+/// signer membership, the recent-signer rate limit, and in-turn/out-of-turn difficulty.
+fn verify_family(state: &CliqueBlockState, header: &Header) -> Result<(), Error> {
+  let signer = recover_creator(header)?;
+
+  if !state.is_authorized(&signer) {
+    return Err(EngineError::NotAuthorized(signer).into());
+  }
+
+  if state.recently_signed(&signer) {
+    return Err(EngineError::NotAuthorized(signer).into());
+  }
+
+  if state.signers.len() != 0 {
+    let expected_difficulty = match state.signer_index(&signer) {
+      Some(index) if (header.number() as usize) % state.signers.len() == index => DIFF_INTURN,
+      _ => DIFF_NOTURN,
+    };
+    if header.difficulty() != &U256::from(expected_difficulty) {
+      return Err(BlockError::InvalidDifficulty(Mismatch {
+        expected: U256::from(expected_difficulty),
+        found: *header.difficulty(),
+      }).into());
+    }
+  }
+
+  Ok(())
+}
+
 const step_time: Duration = Duration::from_millis(100);
 
 impl Clique {
@@ -93,11 +329,105 @@ impl Clique {
   fn is_signer_proposer(&self, bh: &H256) -> bool {
     //let proposer = self.view_proposer(bh, self.height.load(AtomicOrdering::SeqCst), self.view.load(AtomicOrdering::SeqCst));
     //let proposer = self.validators.get(bh);
-    if let Some(_) = self.signers.iter().find(|x| self.signer.read().is_address(x)) {
-      true
+    match self.block_state_by_hash.write().get_mut(bh) {
+      Some(state) => state.signers.as_list().iter().any(|x| self.signer.read().is_address(x)),
+      None => false,
+    }
+  }
+
+  fn client(&self) -> Result<Arc<EngineClient>, Error> {
+    self.client.read().as_ref()
+      .and_then(Weak::upgrade)
+      .ok_or_else(|| Box::new("missing client reference in Clique engine").into())
+  }
+
+  /// Get the `CliqueBlockState` resulting from `header`, i.e. the state of its parent advanced
+  /// by one block. Checkpoint blocks start a fresh state with the signer list embedded in their
+  /// extra-data instead of being derived from their parent.
+  ///
+  /// States are cached by block hash; if the parent's state isn't cached (e.g. we just started
+  /// up, or are catching up via sync) we walk backwards via the `EngineClient` to the nearest
+  /// checkpoint and replay headers forward to reconstruct it.
+  fn state(&self, header: &Header) -> Result<CliqueBlockState, Error> {
+    if let Some(state) = self.block_state_by_hash.write().get_mut(&header.hash()) {
+      return Ok(state.clone());
+    }
+
+    if header.number() % self.epoch_length == 0 {
+      let state = self.state_for_checkpoint(header)?;
+      self.block_state_by_hash.write().insert(header.hash(), state.clone());
+      return Ok(state);
+    }
+
+    let parent_state = self.block_state_by_hash.write().get_mut(header.parent_hash()).cloned();
+    if let Some(parent_state) = parent_state {
+      let mut new_state = parent_state;
+      apply_vote(&mut new_state, header)?;
+      new_state.apply(header, recover_creator(header)?);
+      self.block_state_by_hash.write().insert(header.hash(), new_state.clone());
+      return Ok(new_state);
+    }
+
+    // Parent isn't cached: walk backwards to the most recent checkpoint and replay forward.
+    self.backfill_state(header)
+  }
+
+  /// Build the `CliqueBlockState` for a checkpoint block `header`. Checkpoints cast no votes of
+  /// their own, so the signer list they embed in their extra-data must be exactly the signer set
+  /// carried over from their parent; anything else means the checkpoint was tampered with or a
+  /// vote was dropped, and is rejected. Genesis has no parent and is instead the trust anchor for
+  /// the initial signer set.
+  fn state_for_checkpoint(&self, header: &Header) -> Result<CliqueBlockState, Error> {
+    let signers = if header.number() != 0 {
+      let client = self.client()?;
+      let parent = client.block_header(BlockId::Hash(*header.parent_hash()))
+        .ok_or_else(|| Box::new("missing parent header while validating clique checkpoint").into())?
+        .decode()?;
+      let parent_state = self.state(&parent)?;
+      verify_checkpoint_signers(header, parent_state.signers.as_list())?
     } else {
-      false
+      let mut signers = extract_signers(header)?;
+      signers.sort();
+      signers
+    };
+
+    let mut state = CliqueBlockState::new(SimpleList::new(signers));
+    // genesis carries no seal, so there is no signer to note for it
+    if header.number() != 0 {
+      state.apply(header, recover_creator(header)?);
+    }
+    Ok(state)
+  }
+
+  /// Reconstruct the `CliqueBlockState` for `header` by walking back to the most recent
+  /// checkpoint block, decoding its embedded signer list, and replaying every header in between
+  /// forward through `CliqueBlockState::apply`.
+  fn backfill_state(&self, header: &Header) -> Result<CliqueBlockState, Error> {
+    let client = self.client()?;
+
+    let mut pending = vec![header.clone()];
+    let mut current = header.clone();
+    let mut state = loop {
+      let parent = client.block_header(BlockId::Hash(*current.parent_hash()))
+        .ok_or_else(|| Box::new("missing ancestor header while backfilling clique state").into())?
+        .decode()?;
+
+      if parent.number() % self.epoch_length == 0 {
+        break self.state_for_checkpoint(&parent)?;
+      }
+
+      current = parent.clone();
+      pending.push(parent);
+    };
+
+    for ancestor in pending.into_iter().rev() {
+      apply_vote(&mut state, &ancestor)?;
+      let signer = recover_creator(&ancestor)?;
+      state.apply(&ancestor, signer);
     }
+
+    self.block_state_by_hash.write().insert(header.hash(), state.clone());
+    Ok(state)
   }
 
   pub fn new(our_params: CliqueParams, machine: EthereumMachine) -> Result<Arc<Self>, Error> {
@@ -110,7 +440,9 @@ impl Clique {
 	  Clique {
 		  client: RwLock::new(None),
 		  signer: Default::default(),
-          signers: Default::default(),
+          block_state_by_hash: RwLock::new(LruCache::new(STATE_CACHE_NUM)),
+          proposals: RwLock::new(HashMap::new()),
+          next_seal_attempt: RwLock::new(None),
 		  machine: machine,
 		  step_service: IoService::<Duration>::start()?,
           epoch_length: our_params.epoch,
@@ -124,6 +456,26 @@ impl Clique {
     return Ok(engine);
   }
 
+  /// Queue a vote to authorize `address` as a signer (`authorize == true`) or to remove it
+  /// (`authorize == false`) the next time this node seals a non-checkpoint block. Mirrors the
+  /// `clique_propose` RPC.
+  pub fn propose(&self, address: Address, authorize: bool) {
+    self.proposals.write().insert(address, authorize);
+  }
+
+  /// Stop proposing a vote for `address`. Mirrors the `clique_discard` RPC.
+  pub fn discard(&self, address: &Address) {
+    self.proposals.write().remove(address);
+  }
+
+  /// Pick a queued proposal that would still change `state`, i.e. an authorize vote for an
+  /// address that isn't already a signer, or a drop vote for one that is.
+  fn next_proposal(&self, state: &CliqueBlockState) -> Option<(Address, bool)> {
+    self.proposals.read().iter()
+      .find(|&(address, &authorize)| state.is_authorized(address) != authorize)
+      .map(|(&address, &authorize)| (address, authorize))
+  }
+
   fn sign_header(&self, header: &Header) -> Result<Signature, Error> {
     let digest = sig_hash(header)?;
     if let Ok(sig) = self.signer.read().sign(digest) {
@@ -146,7 +498,31 @@ impl Engine<EthereumMachine> for Clique {
   fn machine(&self) -> &EthereumMachine { &self.machine }
   fn maximum_uncle_count(&self, _block: BlockNumber) -> usize { 0 }
   fn populate_from_parent(&self, header: &mut Header, parent: &Header) {
-    /* ? */
+    let state = match self.state(parent) {
+      Ok(state) => state,
+      Err(_) => return,
+    };
+
+    // Checkpoint blocks cast no votes; their beneficiary must stay zero. All other fields are
+    // still parent-derived, so difficulty below still applies to them.
+    if header.number() % self.epoch_length != 0 {
+      if let Some((candidate, authorize)) = self.next_proposal(&state) {
+        header.set_author(candidate);
+        let nonce = if authorize { *NONCE_AUTH_VOTE } else { *NONCE_DROP_VOTE };
+        header.set_seal(vec![encode(&H256::zero()), encode(&nonce.to_vec())]);
+      }
+    }
+
+    let our_address = state.signers.as_list().iter().find(|x| self.signer.read().is_address(x));
+    let difficulty = if state.signers.len() == 0 {
+      DIFF_NOTURN
+    } else {
+      match our_address.and_then(|addr| state.signer_index(addr)) {
+        Some(index) if (header.number() as usize) % state.signers.len() == index => DIFF_INTURN,
+        _ => DIFF_NOTURN,
+      }
+    };
+    header.set_difficulty(U256::from(difficulty));
   }
 
 
@@ -170,47 +546,119 @@ impl Engine<EthereumMachine> for Clique {
   ///
   /// This operation is synchronous and may (quite reasonably) not be available, in which case
   /// `Seal::None` will be returned.
-  fn generate_seal(&self, block: &ExecutedBlock, _parent: &Header) -> Seal {
-    let mut header = block.header.clone();
+  fn generate_seal(&self, block: &ExecutedBlock, parent: &Header) -> Seal {
+    let header = &block.header;
 
     // don't seal the genesis block
     if header.number() == 0 {
       return Seal::None;
     }
 
-    // if sealing period is 0, refuse to seal
-
-    // let vote_snapshot = self.snapshot.get(bh);
+    let state = match self.state(parent) {
+      Ok(state) => state,
+      Err(_) => return Seal::None,
+    };
 
-    // if we are not authorized to sign, don't seal
+    // if we are not a signer, don't seal
+    let our_address = match state.signers.as_list().iter().find(|x| self.signer.read().is_address(x)) {
+      Some(address) => *address,
+      None => return Seal::None,
+    };
 
     // if we signed recently, don't seal
+    if state.recently_signed(&our_address) {
+      return Seal::None;
+    }
 
-    let authorized = if let Some(pos) = self.signers.iter().position(|x| self.signer.read().is_address(x)) {
-      block.header.number() % ((pos as u64) + 1) == 0 
-    } else {
-      false
-    };
+    // we found ourselves in `state.signers` above, so this can't be empty, but guard anyway
+    // rather than let a reorg-induced race turn this into a divide-by-zero panic
+    if state.signers.len() == 0 {
+      return Seal::None;
+    }
 
-    // sign the digest of the seal
-    if authorized {
-        return Seal::Regular(vec![vec![0,1,2], vec![0,1,2]]);
-    } else {
-      Seal::None
+    let in_turn = state.signer_index(&our_address) == Some((header.number() as usize) % state.signers.len());
+
+    // out-of-turn signers wait a random amount of time before releasing their seal, so that
+    // in-turn signers (which don't wait at all) usually win the race for a given block. Rather
+    // than block this thread on the wiggle, remember the deadline and return `Seal::None` until
+    // it passes; `step()`'s periodic timer drives `update_sealing`, which re-enters here.
+    if !in_turn {
+      let now = Instant::now();
+      let deadline = {
+        let mut next_attempt = self.next_seal_attempt.write();
+        match *next_attempt {
+          Some((number, deadline)) if number == header.number() => deadline,
+          _ => {
+            let wiggle = rand::thread_rng().gen_range(0, state.signers.len() / 2 + 2) as u64;
+            let deadline = now + Duration::from_millis(wiggle * 500);
+            *next_attempt = Some((header.number(), deadline));
+            deadline
+          }
+        }
+      };
+
+      if now < deadline {
+        return Seal::None;
+      }
     }
+
+    // `populate_from_parent` already wrote the vote nonce (if any) into the seal; fall back to
+    // a drop-vote nonce for blocks that aren't voting.
+    let nonce = header.decode_seal::<Vec<&[u8]>>().ok()
+      .and_then(|seal| seal.get(1).map(|n| n.to_vec()))
+      .unwrap_or_else(|| NONCE_DROP_VOTE.to_vec());
+
+    Seal::Regular(vec![encode(&H256::zero()), encode(&nonce)])
   }
 
   fn on_close_block(&self, block: &mut ExecutedBlock) -> Result<(), Error>{
-      /*
-       * TODO:
-      if not checkpoint block:
-        if the block was successfully sealed, then grab the signature from the seal data and
-        append it to the block extraData
-        */
-    trace!(target: "engine", "closing block...");
+    let header = &mut block.header;
+    trace!(target: "engine", "closing block {}", header.number());
+
+    if header.number() == 0 {
+      return Ok(());
+    }
+
+    // vanity is whatever prefix the miner configured; pad/truncate it to the fixed width so the
+    // rest of the extra-data (signer list, signature) lands at a known offset
+    let vanity = {
+      let mut vanity = header.extra_data().clone();
+      vanity.resize(SIGNER_VANITY_LENGTH as usize, 0);
+      vanity
+    };
+
+    let mut extra_data = vanity;
+
+    if header.number() % self.epoch_length == 0 {
+      let client = self.client()?;
+      let parent = client.block_header(BlockId::Hash(*header.parent_hash()))
+        .ok_or_else(|| Box::new("missing parent header when closing checkpoint block").into())?
+        .decode()?;
+      let state = self.state(&parent)?;
+
+      for signer in state.signers.as_list() {
+        extra_data.extend_from_slice(signer.as_bytes());
+      }
+    }
+
+    // reserve the signature suffix, signed below once the rest of the extra-data is final
+    extra_data.extend_from_slice(&[0; SIGNER_SIG_LENGTH as usize]);
+    header.set_extra_data(extra_data);
+
+    let signature = self.sign_header(header)?;
+    let mut extra_data = header.extra_data().clone();
+    let sig_start = extra_data.len() - SIGNER_SIG_LENGTH as usize;
+    extra_data[sig_start..].copy_from_slice(&signature[..]);
+    header.set_extra_data(extra_data);
+
     Ok(())
   }
 
+  // Clique has no genesis-epoch setup to perform here: unlike validator-set engines, the signer
+  // set and pending votes aren't tracked as standalone chain state that needs seeding on import.
+  // They're derived lazily by `state()` straight from each header's author/nonce vote fields, so
+  // the same derivation enacts a vote whether the header was just produced by `on_close_block` or
+  // is being imported from the network — there's nothing left for this hook to do.
   fn on_new_block(
     &self,
     _block: &mut ExecutedBlock,
@@ -227,57 +675,27 @@ impl Engine<EthereumMachine> for Clique {
         }).collect::<Vec<AncestryAction>>().to_vec()
     }
 
-  fn verify_block_basic(&self, _header: &Header) -> Result<(), Error> { 
-      /*
-    if _header.number() == 0 {
-      return Err(Box::new("cannot verify genesis block").into());
-    }
-    */
-
-    // don't allow blocks from the future
-
-    // Checkpoint blocks need to enforce zero beneficiary
-    if _header.number() % self.epoch_length == 0 {
-      if _header.author() != &[0; 20].into() {
-        return Err(Box::new("Checkpoint blocks need to enforce zero beneficiary").into());
-      }
-	  let nonce = _header.decode_seal::<Vec<&[u8]>>().unwrap()[1];
-      if nonce != NONCE_DROP_VOTE {
-        return Err(Box::new("Seal nonce zeros enforced on checkpoints").into());
-      }
-    } else {
-        // TODO
-        // - ensure header extraData has length SIGNER_VANITY_LENGTH + SIGNER_SIG_LENGTH
-        // - ensure header signature corresponds to the right validator for the turn-ness of the
-        // block
-    }
-
-    // Nonces must be 0x00..0 or 0xff..f, zeroes enforced on checkpoints
-
-    // Check that the extra-data contains both the vanity and signature
-
-    // Ensure that the extra-data contains a signer list on checkpoint, but none otherwise
-
-    // Ensure that the mix digest is zero as we don't have fork protection currently
-
-    // Ensure that the block doesn't contain any uncles which are meaningless in PoA
-
-    // Ensure that the block's difficulty is meaningful
+  fn verify_block_basic(&self, header: &Header) -> Result<(), Error> {
+    verify_basic(header, self.epoch_length, self.period)
+  }
 
-    // ...
+  fn verify_block_unordered(&self, header: &Header) -> Result<(), Error> {
+    verify_unordered(header)
+  }
 
-    // TODO verify signer is valid
-    // let signer_address = ec_recover(_header)?.expect(Err(Box::new("fuck").into()));
+  fn verify_block_family(&self, header: &Header, parent: &Header) -> Result<(), Error> {
+    let state = self.state(parent)?;
+    verify_family(&state, header)?;
 
-    Ok(()) 
-  }
+    // `state()` only re-validates a checkpoint's embedded signer list the first time something
+    // derives its state, which may be long after it's accepted into the chain (or never, if no
+    // descendant is ever processed through the live cache). Check it here too, at verification
+    // time, using the parent state we already have.
+    if header.number() % self.epoch_length == 0 {
+      verify_checkpoint_signers(header, state.signers.as_list())?;
+    }
 
-  fn verify_block_unordered(&self, _header: &Header) -> Result<(), Error> {
-	  // Verifying the genesis block is not supported
-	  // Retrieve the snapshot needed to verify this header and cache it
-	  // Resolve the authorization key and check against signers
-	  // Ensure that the difficulty corresponds to the turn-ness of the signer
-	  Ok(())
+    Ok(())
   }
 
 
@@ -324,12 +742,37 @@ impl Engine<EthereumMachine> for Clique {
   }
 
   fn extra_info(&self, header: &Header) -> BTreeMap<String, String> {
-      trace!(target: "engine", "extra info");
-      let mut movie_reviews = BTreeMap::<String, String>::new();
+    let mut info = BTreeMap::new();
+
+    let signer = match recover_creator(header) {
+      Ok(signer) => signer,
+      Err(_) => return info,
+    };
+    info.insert("signer".into(), format!("{:?}", signer));
+
+    let in_turn = header.difficulty() == &U256::from(DIFF_INTURN);
+    info.insert("turn".into(), (if in_turn { "in-turn" } else { "out-of-turn" }).into());
+
+    if header.number() % self.epoch_length == 0 {
+      if let Ok(signers) = extract_signers(header) {
+        info.insert("signers".into(), format!("{:?}", signers));
+      }
+    } else if *header.author() != [0; 20].into() {
+      if let Ok(seal) = header.decode_seal::<Vec<&[u8]>>() {
+        if let Some(&nonce) = seal.get(1) {
+          let vote = if nonce == &NONCE_AUTH_VOTE[..] {
+            "auth"
+          } else if nonce == &NONCE_DROP_VOTE[..] {
+            "drop"
+          } else {
+            "unknown"
+          };
+          info.insert("vote".into(), format!("{:?} {}", header.author(), vote));
+        }
+      }
+    }
 
-      // review some movies.
-      movie_reviews.insert(String::from("Office Space"),       String::from("Deals with real issues in the workplace."));
-      movie_reviews
+    info
   }
 
   fn verify_local_seal(&self, header: &Header) -> Result<(), Error> { Ok(()) }
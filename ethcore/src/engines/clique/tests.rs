@@ -0,0 +1,313 @@
+// Copyright 2015-2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Ethereum.
+
+// Parity Ethereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity Ethereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity Ethereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! EIP-225 regression tests, built directly against `CliqueBlockState` transitions and the
+//! engine's header (de)serialization helpers, without requiring a full client/spec harness.
+
+use ethkey::{Generator, KeyPair, Random};
+use ethereum_types::Address;
+use header::Header;
+
+use ethereum_types::U256;
+
+use super::block_state::CliqueBlockState;
+use super::super::validator_set::SimpleList;
+use super::{sig_hash, recover, extract_signers, verify_basic, verify_unordered, verify_family,
+	verify_checkpoint_signers, SIGNER_VANITY_LENGTH, SIGNER_SIG_LENGTH, NONCE_AUTH_VOTE,
+	NONCE_DROP_VOTE, DIFF_INTURN, DIFF_NOTURN};
+
+/// Build an unsigned header at `number`, optionally casting a vote for `vote` by setting the
+/// beneficiary and nonce, with `extra_data` already containing the vanity (and, for checkpoint
+/// blocks, the embedded signer list) but not yet the signature suffix.
+fn unsigned_header(number: u64, mut extra_data: Vec<u8>, vote: Option<(Address, bool)>) -> Header {
+	extra_data.extend_from_slice(&[0; SIGNER_SIG_LENGTH as usize]);
+
+	let mut header = Header::default();
+	header.set_number(number);
+	header.set_extra_data(extra_data);
+
+	if let Some((candidate, authorize)) = vote {
+		header.set_author(candidate);
+		let nonce = if authorize { *NONCE_AUTH_VOTE } else { *NONCE_DROP_VOTE };
+		header.set_seal(vec![::rlp::encode(&::ethereum_types::H256::zero()), ::rlp::encode(&nonce.to_vec())]);
+	} else {
+		header.set_seal(vec![::rlp::encode(&::ethereum_types::H256::zero()), ::rlp::encode(&NONCE_DROP_VOTE.to_vec())]);
+	}
+
+	header
+}
+
+/// Sign `header` with `key`, writing the 65-byte signature into the extra-data suffix.
+fn sign_header(header: &mut Header, key: &KeyPair) {
+	let hash = sig_hash(header).unwrap();
+	let sig = ::ethkey::sign(key.secret(), &hash).unwrap();
+
+	let mut extra_data = header.extra_data().clone();
+	let sig_start = extra_data.len() - SIGNER_SIG_LENGTH as usize;
+	extra_data[sig_start..].copy_from_slice(&sig[..]);
+	header.set_extra_data(extra_data);
+}
+
+fn vanity() -> Vec<u8> {
+	vec![0; SIGNER_VANITY_LENGTH as usize]
+}
+
+fn checkpoint_extra_data(signers: &[Address]) -> Vec<u8> {
+	let mut sorted = signers.to_vec();
+	sorted.sort();
+
+	let mut extra_data = vanity();
+	for signer in &sorted {
+		extra_data.extend_from_slice(signer.as_bytes());
+	}
+	extra_data
+}
+
+/// The difficulty `state` expects from a block at `number` signed by `signer`, mirroring
+/// `Clique::populate_from_parent`'s in-turn/out-of-turn calculation.
+fn expected_difficulty(state: &CliqueBlockState, number: u64, signer: &Address) -> u8 {
+	match state.signer_index(signer) {
+		Some(index) if (number as usize) % state.signers.len() == index => DIFF_INTURN,
+		_ => DIFF_NOTURN,
+	}
+}
+
+/// Build a header at `number`, signed by `key`, with the difficulty `state` expects of it.
+fn signed_header(state: &CliqueBlockState, number: u64, key: &KeyPair) -> Header {
+	let mut header = unsigned_header(number, vanity(), None);
+	header.set_difficulty(U256::from(expected_difficulty(state, number, &key.address())));
+	sign_header(&mut header, key);
+	header
+}
+
+#[test]
+fn single_signer_is_authorized_and_in_turn() {
+	let signer = Random.generate().unwrap();
+	let state = CliqueBlockState::new(SimpleList::new(vec![signer.address()]));
+
+	assert!(state.is_authorized(&signer.address()));
+	assert_eq!(state.signer_index(&signer.address()), Some(0));
+}
+
+#[test]
+fn multi_signer_round_robin_authorization() {
+	let signers: Vec<_> = (0..3).map(|_| Random.generate().unwrap()).collect();
+	let mut addresses: Vec<_> = signers.iter().map(KeyPair::address).collect();
+	addresses.sort();
+
+	let state = CliqueBlockState::new(SimpleList::new(addresses.clone()));
+	for address in &addresses {
+		assert!(state.is_authorized(address));
+	}
+
+	let outsider = Random.generate().unwrap();
+	assert!(!state.is_authorized(&outsider.address()));
+}
+
+#[test]
+fn majority_vote_adds_a_signer() {
+	let signers: Vec<_> = (0..3).map(|_| Random.generate().unwrap()).collect();
+	let mut addresses: Vec<_> = signers.iter().map(KeyPair::address).collect();
+	addresses.sort();
+
+	let mut state = CliqueBlockState::new(SimpleList::new(addresses.clone()));
+	let candidate = Random.generate().unwrap().address();
+
+	// a single vote isn't enough for 3 existing signers (need more than 3/2, i.e. 2 votes)
+	state.cast_vote(candidate, addresses[0], true);
+	assert!(!state.is_authorized(&candidate));
+
+	// the second vote crosses the majority threshold and the candidate is authorized
+	state.cast_vote(candidate, addresses[1], true);
+	assert!(state.is_authorized(&candidate));
+
+	// the decided vote is no longer pending
+	assert!(!state.votes.contains_key(&(candidate, addresses[0])));
+}
+
+#[test]
+fn majority_vote_removes_a_signer() {
+	let signers: Vec<_> = (0..3).map(|_| Random.generate().unwrap()).collect();
+	let mut addresses: Vec<_> = signers.iter().map(KeyPair::address).collect();
+	addresses.sort();
+
+	let mut state = CliqueBlockState::new(SimpleList::new(addresses.clone()));
+	let target = addresses[2];
+
+	state.cast_vote(target, addresses[0], false);
+	assert!(state.is_authorized(&target));
+
+	state.cast_vote(target, addresses[1], false);
+	assert!(!state.is_authorized(&target));
+}
+
+#[test]
+fn votes_cast_by_a_removed_signer_are_discarded() {
+	let signers: Vec<_> = (0..3).map(|_| Random.generate().unwrap()).collect();
+	let mut addresses: Vec<_> = signers.iter().map(KeyPair::address).collect();
+	addresses.sort();
+
+	let mut state = CliqueBlockState::new(SimpleList::new(addresses.clone()));
+
+	// addresses[2] votes to authorize some other candidate...
+	let stale_candidate = Random.generate().unwrap().address();
+	state.cast_vote(stale_candidate, addresses[2], true);
+	assert!(state.votes.contains_key(&(stale_candidate, addresses[2])));
+
+	// ...then gets voted off the island by the other two signers
+	state.cast_vote(addresses[2], addresses[0], false);
+	state.cast_vote(addresses[2], addresses[1], false);
+	assert!(!state.is_authorized(&addresses[2]));
+
+	// its dangling vote for `stale_candidate` must not survive its removal
+	assert!(!state.votes.contains_key(&(stale_candidate, addresses[2])));
+}
+
+#[test]
+fn checkpoint_blocks_reset_votes_and_embed_the_signer_list() {
+	let signers: Vec<_> = (0..3).map(|_| Random.generate().unwrap()).collect();
+	let mut addresses: Vec<_> = signers.iter().map(KeyPair::address).collect();
+	addresses.sort();
+
+	let mut state = CliqueBlockState::new(SimpleList::new(addresses.clone()));
+	state.cast_vote(Random.generate().unwrap().address(), addresses[0], true);
+	assert!(!state.votes.is_empty());
+
+	let checkpoint = unsigned_header(10, checkpoint_extra_data(&addresses), None);
+	let decoded = extract_signers(&checkpoint).unwrap();
+	assert_eq!(decoded, addresses);
+
+	let fresh_state = CliqueBlockState::new(SimpleList::new(decoded));
+	assert!(fresh_state.votes.is_empty());
+}
+
+#[test]
+fn rejects_a_block_signed_by_a_non_authorized_key() {
+	let signer = Random.generate().unwrap();
+	let outsider = Random.generate().unwrap();
+
+	let state = CliqueBlockState::new(SimpleList::new(vec![signer.address()]));
+
+	let header = signed_header(&state, 1, &outsider);
+
+	let recovered = ::ethkey::public_to_address(&recover(&header).unwrap());
+	assert_eq!(recovered, outsider.address());
+	assert!(!state.is_authorized(&recovered));
+
+	match verify_family(&state, &header) {
+		Err(_) => (),
+		Ok(()) => panic!("expected verify_family to reject a non-authorized signer"),
+	}
+}
+
+#[test]
+fn rejects_a_signer_signing_twice_within_the_recent_window() {
+	let signers: Vec<_> = (0..3).map(|_| Random.generate().unwrap()).collect();
+	let mut addresses: Vec<_> = signers.iter().map(KeyPair::address).collect();
+	addresses.sort();
+	let key_for = |address: &Address| -> &KeyPair {
+		signers.iter().find(|k| &k.address() == address).unwrap()
+	};
+
+	// floor(3 / 2) + 1 == 2: a signer may not sign again until at least one other signer has
+	let mut state = CliqueBlockState::new(SimpleList::new(addresses.clone()));
+
+	let header_1 = unsigned_header(1, vanity(), None);
+	state.apply(&header_1, addresses[0]);
+	assert!(state.recently_signed(&addresses[0]));
+
+	// gap == 1 < limit (2): addresses[0] signing block 2 right after block 1 must be rejected
+	let state_after_block_1 = state.clone();
+	let rejected_header = signed_header(&state_after_block_1, 2, key_for(&addresses[0]));
+	match verify_family(&state_after_block_1, &rejected_header) {
+		Err(_) => (),
+		Ok(()) => panic!("expected verify_family to reject signing within the recent window"),
+	}
+
+	// after another signer takes block 2, addresses[0] is no longer rate-limited...
+	let header_2 = unsigned_header(2, vanity(), None);
+	state.apply(&header_2, addresses[1]);
+	assert!(!state.recently_signed(&addresses[0]));
+
+	// ...and gap == 2 == limit is exactly the EIP-225 boundary that must be allowed
+	let allowed_header = signed_header(&state, 3, key_for(&addresses[0]));
+	verify_family(&state, &allowed_header).expect("gap == limit must be allowed");
+}
+
+#[test]
+fn verify_basic_rejects_an_unsorted_checkpoint_signer_list() {
+	let signers: Vec<_> = (0..3).map(|_| Random.generate().unwrap()).collect();
+	let mut addresses: Vec<_> = signers.iter().map(KeyPair::address).collect();
+	addresses.sort();
+
+	let mut unsorted = checkpoint_extra_data(&addresses);
+	// swap the first two embedded signer entries so the list is no longer sorted
+	let vanity_len = SIGNER_VANITY_LENGTH as usize;
+	for i in 0..20 {
+		unsorted.swap(vanity_len + i, vanity_len + 20 + i);
+	}
+
+	let mut header = unsigned_header(10, unsorted, None);
+	header.set_difficulty(U256::from(DIFF_NOTURN));
+	header.set_uncles_hash(::hash::KECCAK_EMPTY_LIST_RLP);
+	sign_header(&mut header, &signers[0]);
+
+	match verify_basic(&header, 10, 15) {
+		Err(_) => (),
+		Ok(()) => panic!("expected verify_basic to reject an unsorted checkpoint signer list"),
+	}
+}
+
+#[test]
+fn verify_basic_accepts_a_well_formed_non_checkpoint_header() {
+	let signer = Random.generate().unwrap();
+	let mut header = unsigned_header(1, vanity(), None);
+	header.set_difficulty(U256::from(DIFF_INTURN));
+	header.set_uncles_hash(::hash::KECCAK_EMPTY_LIST_RLP);
+	sign_header(&mut header, &signer);
+
+	verify_basic(&header, 10, 15).expect("a well-formed header must pass verify_basic");
+}
+
+#[test]
+fn verify_checkpoint_signers_rejects_a_forged_signer_list() {
+	let signers: Vec<_> = (0..3).map(|_| Random.generate().unwrap()).collect();
+	let mut addresses: Vec<_> = signers.iter().map(KeyPair::address).collect();
+	addresses.sort();
+
+	// the checkpoint claims a signer set that doesn't match the parent state's
+	let forged = vec![Random.generate().unwrap().address(), Random.generate().unwrap().address()];
+	let checkpoint = unsigned_header(10, checkpoint_extra_data(&forged), None);
+
+	match verify_checkpoint_signers(&checkpoint, &addresses) {
+		Err(_) => (),
+		Ok(_) => panic!("expected a forged checkpoint signer list to be rejected"),
+	}
+
+	// the true signer set, embedded honestly, must be accepted
+	let honest = unsigned_header(10, checkpoint_extra_data(&addresses), None);
+	assert_eq!(verify_checkpoint_signers(&honest, &addresses).unwrap(), addresses);
+}
+
+#[test]
+fn verify_unordered_accepts_any_correctly_signed_header() {
+	let signer = Random.generate().unwrap();
+	let mut header = unsigned_header(1, vanity(), None);
+	header.set_difficulty(U256::from(DIFF_INTURN));
+	sign_header(&mut header, &signer);
+
+	verify_unordered(&header).expect("verify_unordered has no opinion on parent-derived state");
+}